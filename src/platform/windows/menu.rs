@@ -1,22 +1,40 @@
 use std::any::Any;
+use std::cell::Cell;
+use std::collections::HashMap;
 use std::mem::zeroed;
 use std::ptr::null_mut;
 
 use windows_sys::Win32::Foundation::HWND;
 use windows_sys::Win32::UI::WindowsAndMessaging::{
-    AppendMenuW, CreatePopupMenu, DestroyMenu, GetCursorPos, SetForegroundWindow, TrackPopupMenu, 
-    SetMenuItemInfoW, GetMenuItemCount, HMENU, MF_CHECKED, MF_GRAYED, MF_POPUP, MF_SEPARATOR, MF_STRING, 
-    TPM_BOTTOMALIGN, TPM_LEFTALIGN, MENUITEMINFOW, MIIM_BITMAP
+    AppendMenuW, CheckMenuItem, CreateAcceleratorTableW, CreatePopupMenu, DestroyAcceleratorTable,
+    DestroyMenu, EnableMenuItem, GetCursorPos, SetForegroundWindow, TrackPopupMenu, SetMenuItemBitmaps,
+    SetMenuItemInfoW, GetMenuItemCount, ACCEL, FALT, FCONTROL, FSHIFT, FVIRTKEY, HACCEL, HMENU, MF_BYCOMMAND,
+    MF_CHECKED, MF_ENABLED, MF_GRAYED, MF_POPUP, MF_SEPARATOR, MF_STRING, MF_UNCHECKED,
+    TPM_BOTTOMALIGN, TPM_LEFTALIGN, MENUITEMINFOW, MFT_RADIOCHECK, MIIM_BITMAP, MIIM_FTYPE, MIIM_STRING
 };
 use windows_sys::Win32::Graphics::Gdi::{DeleteObject, HBITMAP};
 
 use crate::error::{TrayError, TrayResult};
 use crate::platform::windows::{encode_wide, error_check, NativeIcon};
-use crate::{Menu, MenuItem};
+use crate::{Accelerator, Menu, MenuItem};
+
+fn accel_flags(shortcut: &Accelerator) -> u8 {
+    let mut f_virt = FVIRTKEY as u8;
+    if shortcut.ctrl {
+        f_virt |= FCONTROL as u8;
+    }
+    if shortcut.alt {
+        f_virt |= FALT as u8;
+    }
+    if shortcut.shift {
+        f_virt |= FSHIFT as u8;
+    }
+    f_virt
+}
 
 fn set_menu_icon(hmenu: HMENU, item_id: u32, by_position: bool, icon: NativeIcon, bitmaps: &mut Vec<HBITMAP>) -> TrayResult<()> {
     let bitmap = icon.to_bitmap()?;
-    
+
     unsafe {
         let mut menu_info: MENUITEMINFOW = zeroed();
         menu_info.cbSize = std::mem::size_of::<MENUITEMINFOW>() as u32;
@@ -25,21 +43,61 @@ fn set_menu_icon(hmenu: HMENU, item_id: u32, by_position: bool, icon: NativeIcon
 
         let by_position_flag = if by_position { 1 } else { 0 };
         error_check(SetMenuItemInfoW(hmenu, item_id, by_position_flag, &menu_info))?;
-        
+
         bitmaps.push(bitmap);
     }
     Ok(())
 }
 
+fn set_radio_check_type(hmenu: HMENU, menu_id: u32) -> TrayResult<()> {
+    unsafe {
+        let mut menu_info: MENUITEMINFOW = zeroed();
+        menu_info.cbSize = std::mem::size_of::<MENUITEMINFOW>() as u32;
+        menu_info.fMask = MIIM_FTYPE;
+        menu_info.fType = MFT_RADIOCHECK;
+
+        error_check(SetMenuItemInfoW(hmenu, menu_id, 0, &menu_info))?;
+    }
+    Ok(())
+}
+
+fn set_check_marks(hmenu: HMENU, menu_id: u32, unchecked: NativeIcon, checked: NativeIcon, bitmaps: &mut Vec<HBITMAP>) -> TrayResult<()> {
+    let unchecked_bitmap = unchecked.to_bitmap()?;
+    bitmaps.push(unchecked_bitmap);
+    let checked_bitmap = checked.to_bitmap()?;
+    bitmaps.push(checked_bitmap);
+
+    error_check(unsafe { SetMenuItemBitmaps(hmenu, menu_id, MF_BYCOMMAND, unchecked_bitmap, checked_bitmap) })?;
+    Ok(())
+}
+
 pub struct NativeMenu {
     hmenu: HMENU,
     signals_map: Box<dyn SignalMap>,
     bitmaps: Vec<HBITMAP>,
+    // menu_id -> (hmenu, every menu_id in its group, including itself) for every radio item, so
+    // a later click can reselect its group via `set_radio_selected` without assuming the group's
+    // items are laid out contiguously.
+    radio_items: HashMap<u16, (HMENU, Vec<u16>)>,
+    haccel: HACCEL,
+    // Set for the duration of `show_on_cursor`, since `TrackPopupMenu` runs its own nested
+    // message loop and a command handler invoked from it must not mutate the menu it's shown from.
+    tracking: Cell<bool>,
+    // Every popup menu created for a `MenuItem::Menu`, tracked by handle rather than by the
+    // pointer-as-id passed to `AppendMenuW` so a failed append still gets cleaned up.
+    submenus: Vec<HMENU>,
 }
 
 impl NativeMenu {
+    /// Handle to the accelerator table built from each item's `shortcut`, for use with
+    /// `TranslateAcceleratorW` in the caller's own message loop.
+    pub fn haccel(&self) -> HACCEL {
+        self.haccel
+    }
+
     pub fn show_on_cursor(&self, hwnd: HWND) -> TrayResult<()> {
-        unsafe {
+        self.tracking.set(true);
+        let result = (|| unsafe {
             let mut cursor = zeroed();
             error_check(GetCursorPos(&mut cursor))?;
             error_check(SetForegroundWindow(hwnd))?;
@@ -52,13 +110,64 @@ impl NativeMenu {
                 hwnd,
                 null_mut()
             ))?;
-        }
-        Ok(())
+            Ok(())
+        })();
+        self.tracking.set(false);
+        result
     }
 
     pub fn map(&self, id: u16) -> Option<&dyn Any> {
         self.signals_map.map(id)
     }
+
+    /// Check or uncheck the button with the given `menu_id` (its position in the signal map).
+    pub fn set_checked(&self, menu_id: u16, checked: bool) -> TrayResult<()> {
+        self.guard_tracking()?;
+        let flags = MF_BYCOMMAND | if checked { MF_CHECKED } else { MF_UNCHECKED };
+        error_check(unsafe { CheckMenuItem(self.hmenu, menu_id as u32, flags) })?;
+        Ok(())
+    }
+
+    /// Enable or gray out the button with the given `menu_id`.
+    pub fn set_enabled(&self, menu_id: u16, enabled: bool) -> TrayResult<()> {
+        self.guard_tracking()?;
+        let flags = MF_BYCOMMAND | if enabled { MF_ENABLED } else { MF_GRAYED };
+        error_check(unsafe { EnableMenuItem(self.hmenu, menu_id as u32, flags) })?;
+        Ok(())
+    }
+
+    /// Select the radio item with the given `menu_id`, deselecting the other members of its group.
+    pub fn set_radio_selected(&self, menu_id: u16) -> TrayResult<()> {
+        self.guard_tracking()?;
+        let (hmenu, members) = self.radio_items.get(&menu_id)
+            .ok_or_else(|| TrayError::Custom("not a radio menu item".into()))?;
+        for &member in members {
+            let flags = MF_BYCOMMAND | if member == menu_id { MF_CHECKED } else { MF_UNCHECKED };
+            error_check(unsafe { CheckMenuItem(*hmenu, member as u32, flags) })?;
+        }
+        Ok(())
+    }
+
+    /// Replace the label of the button with the given `menu_id`.
+    pub fn set_label(&self, menu_id: u16, label: &str) -> TrayResult<()> {
+        self.guard_tracking()?;
+        let mut wide = encode_wide(label);
+        unsafe {
+            let mut menu_info: MENUITEMINFOW = zeroed();
+            menu_info.cbSize = std::mem::size_of::<MENUITEMINFOW>() as u32;
+            menu_info.fMask = MIIM_STRING;
+            menu_info.dwTypeData = wide.as_mut_ptr();
+            error_check(SetMenuItemInfoW(self.hmenu, menu_id as u32, 0, &menu_info))?;
+        }
+        Ok(())
+    }
+
+    fn guard_tracking(&self) -> TrayResult<()> {
+        if self.tracking.get() {
+            return Err(TrayError::Custom("cannot edit the menu while it is being shown".into()));
+        }
+        Ok(())
+    }
 }
 
 impl Drop for NativeMenu {
@@ -69,19 +178,45 @@ impl Drop for NativeMenu {
                 log::warn!("Failed to destroy menu bitmap: {err}")
             }
         }
+        // Destroy submenus before the top-level hmenu: a successfully-attached submenu is
+        // already invalid once DestroyMenu(hmenu) cascades, so it must be destroyed first.
+        // Submenus are recorded parent-before-child, so walk them in reverse to destroy nested
+        // submenus before the submenu that contains them.
+        for submenu in self.submenus.iter().rev() {
+            if let Err(err) = error_check(unsafe { DestroyMenu(*submenu) }) {
+                log::warn!("Failed to destroy submenu: {err}")
+            }
+        }
         if let Err(err) = error_check(unsafe { DestroyMenu(self.hmenu) }) {
             log::warn!("Failed to destroy native menu: {err}")
         }
+        if !self.haccel.is_null() {
+            if let Err(err) = error_check(unsafe { DestroyAcceleratorTable(self.haccel) }) {
+                log::warn!("Failed to destroy menu accelerator table: {err}")
+            }
+        }
     }
 }
 
-fn add_all<T>(hmenu: HMENU, signals: &mut Vec<T>, items: Vec<MenuItem<T>>, bitmaps: &mut Vec<HBITMAP>) -> TrayResult<()> {
+fn add_all<T>(
+    hmenu: HMENU,
+    signals: &mut Vec<T>,
+    items: Vec<MenuItem<T>>,
+    bitmaps: &mut Vec<HBITMAP>,
+    radio_items: &mut HashMap<u16, (HMENU, Vec<u16>)>,
+    accels: &mut Vec<ACCEL>,
+    submenus: &mut Vec<HMENU>,
+) -> TrayResult<()> {
+    // group -> (selected menu_id, every member's menu_id, in the order they were added). Members
+    // are checked/unchecked individually by command id, so they don't need to be contiguous.
+    let mut radio_groups: HashMap<u32, (u16, Vec<u16>)> = HashMap::new();
+
     for item in items {
         match item {
             MenuItem::Separator => {
                 error_check(unsafe { AppendMenuW(hmenu, MF_SEPARATOR, 0, null_mut()) })?;
             }
-            MenuItem::Button { name, signal, disabled, checked, icon } => {
+            MenuItem::Button { name, signal, disabled, checked, icon, shortcut, check_marks } => {
                 let mut flags = MF_STRING;
                 if let Some(true) = checked {
                     flags |= MF_CHECKED;
@@ -89,28 +224,67 @@ fn add_all<T>(hmenu: HMENU, signals: &mut Vec<T>, items: Vec<MenuItem<T>>, bitma
                 if disabled {
                     flags |= MF_GRAYED;
                 }
-                let wide = encode_wide(&name);
                 let menu_id = signals.len();
+                let label = match &shortcut {
+                    Some(shortcut) => format!("{name}\t{shortcut}"),
+                    None => name,
+                };
+                let wide = encode_wide(&label);
                 error_check(unsafe { AppendMenuW(hmenu, flags, menu_id, wide.as_ptr()) })?;
-                
+
+                if let Some(icon) = icon {
+                    set_menu_icon(hmenu, menu_id as u32, false, icon.into(), bitmaps)?;
+                }
+
+                if let Some((unchecked, checked)) = check_marks {
+                    set_check_marks(hmenu, menu_id as u32, unchecked.into(), checked.into(), bitmaps)?;
+                }
+
+                if let Some(shortcut) = shortcut {
+                    accels.push(ACCEL {
+                        fVirt: accel_flags(&shortcut),
+                        key: shortcut.key,
+                        cmd: menu_id as u16,
+                    });
+                }
+
+                signals.push(signal);
+            }
+            MenuItem::Radio { name, signal, group, checked, disabled, icon } => {
+                let mut flags = MF_STRING;
+                if disabled {
+                    flags |= MF_GRAYED;
+                }
+                let wide = encode_wide(&name);
+                let menu_id = signals.len() as u16;
+                error_check(unsafe { AppendMenuW(hmenu, flags, menu_id as usize, wide.as_ptr()) })?;
+                set_radio_check_type(hmenu, menu_id as u32)?;
+
                 if let Some(icon) = icon {
                     set_menu_icon(hmenu, menu_id as u32, false, icon.into(), bitmaps)?;
                 }
-                
+
+                let entry = radio_groups.entry(group).or_insert((menu_id, Vec::new()));
+                if checked {
+                    entry.0 = menu_id;
+                }
+                entry.1.push(menu_id);
+
                 signals.push(signal);
             }
             MenuItem::Menu { name, children, icon } => {
                 let submenu = error_check(unsafe { CreatePopupMenu() })?;
-                add_all(submenu, signals, children, bitmaps)?;
+                submenus.push(submenu);
+                add_all(submenu, signals, children, bitmaps, radio_items, accels, submenus)?;
                 let wide = encode_wide(&name);
                 let submenu_id = submenu as usize;
                 error_check(unsafe { AppendMenuW(hmenu, MF_POPUP, submenu_id, wide.as_ptr()) })?;
-                
+
                 if let Some(icon) = icon {
-                    let submenu_position = unsafe { 
-                        GetMenuItemCount(hmenu) - 1 
+                    let submenu_position = unsafe {
+                        GetMenuItemCount(hmenu) - 1
                     };
-                    
+
                     if submenu_position >= 0 {
                         if let Err(e) = set_menu_icon(hmenu, submenu_position as u32, true, icon.into(), bitmaps) {
                             log::debug!("Failed to set submenu icon: {}", e);
@@ -120,6 +294,17 @@ fn add_all<T>(hmenu: HMENU, signals: &mut Vec<T>, items: Vec<MenuItem<T>>, bitma
             }
         }
     }
+
+    for (selected, members) in radio_groups.into_values() {
+        for &member in &members {
+            let flags = MF_BYCOMMAND | if member == selected { MF_CHECKED } else { MF_UNCHECKED };
+            error_check(unsafe { CheckMenuItem(hmenu, member as u32, flags) })?;
+        }
+        for &member in &members {
+            radio_items.insert(member, (hmenu, members.clone()));
+        }
+    }
+
     Ok(())
 }
 
@@ -129,14 +314,30 @@ impl<T: 'static> TryFrom<Menu<T>> for NativeMenu {
     fn try_from(value: Menu<T>) -> Result<Self, Self::Error> {
         log::trace!("Creating new native menu");
         let hmenu = error_check(unsafe { CreatePopupMenu() })?;
-        let mut signals = Vec::<T>::new();
-        let mut bitmaps = Vec::<HBITMAP>::new();
-        add_all(hmenu, &mut signals, value.items, &mut bitmaps)?;
-        Ok(Self {
+
+        // Build directly into `Self` so that, if `add_all` fails partway through, the partially
+        // built menu's own `Drop` cleans up whatever handles it already created instead of
+        // leaking them.
+        let mut menu = Self {
             hmenu,
-            signals_map: Box::new(signals),
-            bitmaps,
-        })
+            signals_map: Box::new(Vec::<T>::new()),
+            bitmaps: Vec::new(),
+            radio_items: HashMap::new(),
+            haccel: null_mut(),
+            tracking: Cell::new(false),
+            submenus: Vec::new(),
+        };
+
+        let mut signals = Vec::<T>::new();
+        let mut accels = Vec::<ACCEL>::new();
+        add_all(menu.hmenu, &mut signals, value.items, &mut menu.bitmaps, &mut menu.radio_items, &mut accels, &mut menu.submenus)?;
+
+        if !accels.is_empty() {
+            menu.haccel = error_check(unsafe { CreateAcceleratorTableW(accels.as_ptr(), accels.len() as i32) })?;
+        }
+
+        menu.signals_map = Box::new(signals);
+        Ok(menu)
     }
 }
 